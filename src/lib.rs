@@ -11,16 +11,27 @@ use winapi::shared::dxgi::{
     CreateDXGIFactory1, IDXGIAdapter, IDXGIAdapter1, IDXGIFactory1, IDXGIOutput, IDXGISurface1,
     IID_IDXGIFactory1, DXGI_MAP_READ, DXGI_OUTPUT_DESC, DXGI_RESOURCE_PRIORITY_MAXIMUM,
 };
-use winapi::shared::dxgi1_2::{IDXGIOutput1, IDXGIOutputDuplication};
+use winapi::shared::dxgi1_2::{
+    IDXGIOutput1, IDXGIOutputDuplication, DXGI_OUTDUPL_MOVE_RECT,
+    DXGI_OUTDUPL_POINTER_SHAPE_TYPE_COLOR, DXGI_OUTDUPL_POINTER_SHAPE_TYPE_MASKED_COLOR,
+    DXGI_OUTDUPL_POINTER_SHAPE_TYPE_MONOCHROME,
+};
+use winapi::shared::dxgi1_5::{IDXGIOutput5, IID_IDXGIOutput5};
+use winapi::shared::dxgiformat::{DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_R16G16B16A16_FLOAT};
 use winapi::shared::dxgitype::*;
 // use winapi::shared::ntdef::*;
 use winapi::shared::windef::*;
 use winapi::shared::winerror::*;
 use winapi::um::d3d11::*;
 use winapi::um::d3dcommon::*;
+use winapi::um::d3dcompiler::{D3DCompile, D3DCOMPILE_ENABLE_STRICTNESS, D3DCOMPILE_OPTIMIZATION_LEVEL3};
 use winapi::um::unknwnbase::*;
+use winapi::um::winnt::GENERIC_ALL;
 use winapi::um::winuser::*;
 use wio::com::ComPtr;
+use std::ffi::CString;
+use std::thread;
+use std::time::Duration;
 
 /// Color represented by additive channels: Blue (b), Green (g), Red (r), and Alpha (a).
 #[derive(Copy, Clone, Debug, PartialOrd, PartialEq, Eq, Ord)]
@@ -31,6 +42,94 @@ pub struct BGRA8 {
     pub a: u8,
 }
 
+/// A single HDR pixel as produced by `DXGIManager::capture_frame_f16`, i.e.
+/// `DXGI_FORMAT_R16G16B16A16_FLOAT`. Each channel is the raw IEEE 754 binary16 bit pattern, not a
+/// converted float; callers that want float values or tone-mapping should decode and process them
+/// as scRGB themselves.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RGBA16F {
+    pub r: u16,
+    pub g: u16,
+    pub b: u16,
+    pub a: u16,
+}
+
+/// A rectangle, in desktop coordinates, as reported by `IDXGIOutputDuplication::GetFrameDirtyRects`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DirtyRect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl From<RECT> for DirtyRect {
+    fn from(r: RECT) -> Self {
+        DirtyRect {
+            left: r.left,
+            top: r.top,
+            right: r.right,
+            bottom: r.bottom,
+        }
+    }
+}
+
+/// A region that was simply moved, rather than redrawn, between two frames, as reported by
+/// `IDXGIOutputDuplication::GetFrameMoveRects`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MoveRect {
+    /// Top-left corner the region was moved from
+    pub source_point: (i32, i32),
+    /// Rectangle, in desktop coordinates, the region was moved to
+    pub destination_rect: DirtyRect,
+}
+
+impl From<DXGI_OUTDUPL_MOVE_RECT> for MoveRect {
+    fn from(r: DXGI_OUTDUPL_MOVE_RECT) -> Self {
+        MoveRect {
+            source_point: (r.SourcePoint.x, r.SourcePoint.y),
+            destination_rect: r.DestinationRect.into(),
+        }
+    }
+}
+
+/// The pixel format a captured cursor shape is encoded in, as reported by
+/// `IDXGIOutputDuplication::GetFramePointerShape`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PointerShapeType {
+    /// 1bpp AND mask followed by a 1bpp XOR mask, each `height / 2` rows tall
+    Monochrome,
+    /// Straightforward 32bpp BGRA, alpha-blended onto the desktop
+    Color,
+    /// 32bpp BGRA where the alpha byte selects AND/XOR compositing per pixel
+    MaskedColor,
+}
+
+/// A cached hardware cursor shape. Only delivered by DXGI when the shape changes, so it is kept
+/// around between frames in `DuplicatedOutput`/`DXGIManager`.
+#[derive(Clone, Debug)]
+pub struct CursorShape {
+    pub shape_type: PointerShapeType,
+    pub width: u32,
+    pub height: u32,
+    /// Row pitch, in bytes, of `pixels`
+    pub pitch: u32,
+    pub hot_spot: (i32, i32),
+    /// Raw pixel/mask bytes, in the encoding described by `shape_type`
+    pub pixels: Vec<u8>,
+}
+
+/// Cursor state as reported alongside a captured frame.
+#[derive(Clone, Debug)]
+pub struct CursorInfo {
+    pub visible: bool,
+    /// Top-left position of the cursor, relative to the output it was captured from
+    pub position: (i32, i32),
+    pub last_update_time: i64,
+    /// The cursor's shape, if one has been received yet
+    pub shape: Option<CursorShape>,
+}
+
 /// Possible errors when capturing
 #[derive(Debug)]
 pub enum CaptureError {
@@ -139,9 +238,57 @@ fn get_capture_source(
     }
 }
 
+/// Default number of times in total to attempt `IDXGIOutput1::DuplicateOutput` before giving up,
+/// as used by WebRTC's DirectX screen capturer to ride out transient failures during mode changes.
+const DEFAULT_DUPLICATE_OUTPUT_RETRIES: u32 = 10;
+/// Default wait between `DuplicateOutput` retries.
+const DEFAULT_DUPLICATE_OUTPUT_RETRY_INTERVAL_MS: u32 = 50;
+
+/// Attach the calling thread to the current input desktop, so that output duplication can
+/// succeed even when the foreground session is on a different (e.g. secure/UAC) desktop. This
+/// mirrors the approach GStreamer's dxgicapture element takes to avoid `E_ACCESSDENIED`.
+fn attach_thread_to_input_desktop() {
+    unsafe {
+        let desktop = OpenInputDesktop(0, 0, GENERIC_ALL);
+        if !desktop.is_null() {
+            SetThreadDesktop(desktop);
+            CloseDesktop(desktop);
+        }
+    }
+}
+
+/// Call `output.DuplicateOutput` up to `retries` times in total, with `retry_interval_ms` between
+/// attempts, since the call routinely fails transiently while the display mode is changing. With
+/// `retries` of 0 no attempt is made and `E_FAIL` is returned.
+fn duplicate_output_with_retry(
+    output: &IDXGIOutput1,
+    device: *mut IUnknown,
+    retries: u32,
+    retry_interval_ms: u32,
+) -> Result<ComPtr<IDXGIOutputDuplication>, HRESULT> {
+    let mut last_hr = E_FAIL;
+    for attempt in 0..retries {
+        let hr = unsafe {
+            let mut output_duplication = ptr::null_mut();
+            let hr = output.DuplicateOutput(device, &mut output_duplication);
+            if !hr_failed(hr) {
+                return Ok(ComPtr::from_raw(output_duplication));
+            }
+            hr
+        };
+        last_hr = hr;
+        if attempt + 1 < retries {
+            thread::sleep(Duration::from_millis(retry_interval_ms as u64));
+        }
+    }
+    Err(last_hr)
+}
+
 fn duplicate_outputs(
     mut device: ComPtr<ID3D11Device>,
     outputs: Vec<ComPtr<IDXGIOutput>>,
+    retries: u32,
+    retry_interval_ms: u32,
 ) -> Result<
     (
         ComPtr<ID3D11Device>,
@@ -155,25 +302,564 @@ fn duplicate_outputs(
         .map(|out| out.cast::<IDXGIOutput1>().unwrap())
     {
         let dxgi_device = device.up::<IUnknown>();
-        let output_duplication = unsafe {
+        let output_duplication =
+            duplicate_output_with_retry(&output, dxgi_device.as_raw(), retries, retry_interval_ms)
+                .map_err(|hr| hr)?;
+        device = dxgi_device.cast().unwrap();
+        out_dups.push((output_duplication, output));
+    }
+    Ok((device, out_dups))
+}
+
+/// Like `duplicate_output_with_retry`, but negotiates HDR/wide-gamut capture via
+/// `IDXGIOutput5::DuplicateOutput1`, requesting `DXGI_FORMAT_R16G16B16A16_FLOAT` in addition to
+/// the default 8-bit format so the output can hand back whichever it needs.
+fn duplicate_output1_with_retry(
+    output: &IDXGIOutput1,
+    device: *mut IUnknown,
+    retries: u32,
+    retry_interval_ms: u32,
+) -> Result<ComPtr<IDXGIOutputDuplication>, HRESULT> {
+    let output5 = unsafe {
+        let mut output5 = ptr::null_mut();
+        let hr = output.QueryInterface(&IID_IDXGIOutput5, &mut output5);
+        if hr_failed(hr) {
+            return Err(hr);
+        }
+        ComPtr::from_raw(output5 as *mut IDXGIOutput5)
+    };
+    let supported_formats = [DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_R16G16B16A16_FLOAT];
+    let mut last_hr = E_FAIL;
+    for attempt in 0..retries {
+        let hr = unsafe {
             let mut output_duplication = ptr::null_mut();
-            let hr = output.DuplicateOutput(dxgi_device.as_raw(), &mut output_duplication);
-            if hr_failed(hr) {
-                return Err(hr);
+            let hr = output5.DuplicateOutput1(
+                device,
+                0,
+                supported_formats.len() as u32,
+                supported_formats.as_ptr(),
+                &mut output_duplication,
+            );
+            if !hr_failed(hr) {
+                return Ok(ComPtr::from_raw(output_duplication));
             }
-            ComPtr::from_raw(output_duplication)
+            hr
         };
+        last_hr = hr;
+        if attempt + 1 < retries {
+            thread::sleep(Duration::from_millis(retry_interval_ms as u64));
+        }
+    }
+    Err(last_hr)
+}
+
+/// Like `duplicate_outputs`, but acquires each output via `duplicate_output1_with_retry` for HDR
+/// capture.
+fn duplicate_outputs_hdr(
+    mut device: ComPtr<ID3D11Device>,
+    outputs: Vec<ComPtr<IDXGIOutput>>,
+    retries: u32,
+    retry_interval_ms: u32,
+) -> Result<
+    (
+        ComPtr<ID3D11Device>,
+        Vec<(ComPtr<IDXGIOutputDuplication>, ComPtr<IDXGIOutput1>)>,
+    ),
+    HRESULT,
+> {
+    let mut out_dups = Vec::new();
+    for output in outputs
+        .into_iter()
+        .map(|out| out.cast::<IDXGIOutput1>().unwrap())
+    {
+        let dxgi_device = device.up::<IUnknown>();
+        let output_duplication =
+            duplicate_output1_with_retry(&output, dxgi_device.as_raw(), retries, retry_interval_ms)
+                .map_err(|hr| hr)?;
         device = dxgi_device.cast().unwrap();
         out_dups.push((output_duplication, output));
     }
     Ok((device, out_dups))
 }
 
+/// Fetch the dirty rectangles for the currently acquired frame, resizing the query buffer and
+/// retrying if DXGI reports that it needs more space than `frame_info.TotalMetadataBufferSize`
+/// suggested.
+fn get_frame_dirty_rects(
+    output_duplication: &IDXGIOutputDuplication,
+    metadata_buffer_size: u32,
+) -> Result<Vec<RECT>, HRESULT> {
+    let mut capacity = metadata_buffer_size as usize / mem::size_of::<RECT>();
+    loop {
+        let mut dirty_rects: Vec<RECT> = Vec::with_capacity(capacity);
+        let mut bytes_required = 0;
+        let hr = unsafe {
+            output_duplication.GetFrameDirtyRects(
+                (dirty_rects.capacity() * mem::size_of::<RECT>()) as u32,
+                dirty_rects.as_mut_ptr(),
+                &mut bytes_required,
+            )
+        };
+        if hr == DXGI_ERROR_MORE_DATA {
+            capacity = bytes_required as usize / mem::size_of::<RECT>();
+        } else if hr_failed(hr) {
+            return Err(hr);
+        } else {
+            unsafe { dirty_rects.set_len(bytes_required as usize / mem::size_of::<RECT>()) };
+            return Ok(dirty_rects);
+        }
+    }
+}
+
+/// Fetch the move rectangles for the currently acquired frame, resizing the query buffer and
+/// retrying if DXGI reports that it needs more space.
+fn get_frame_move_rects(
+    output_duplication: &IDXGIOutputDuplication,
+    metadata_buffer_size: u32,
+) -> Result<Vec<DXGI_OUTDUPL_MOVE_RECT>, HRESULT> {
+    let mut capacity = metadata_buffer_size as usize / mem::size_of::<DXGI_OUTDUPL_MOVE_RECT>();
+    loop {
+        let mut move_rects: Vec<DXGI_OUTDUPL_MOVE_RECT> = Vec::with_capacity(capacity);
+        let mut bytes_required = 0;
+        let hr = unsafe {
+            output_duplication.GetFrameMoveRects(
+                (move_rects.capacity() * mem::size_of::<DXGI_OUTDUPL_MOVE_RECT>()) as u32,
+                move_rects.as_mut_ptr(),
+                &mut bytes_required,
+            )
+        };
+        if hr == DXGI_ERROR_MORE_DATA {
+            capacity = bytes_required as usize / mem::size_of::<DXGI_OUTDUPL_MOVE_RECT>();
+        } else if hr_failed(hr) {
+            return Err(hr);
+        } else {
+            unsafe {
+                move_rects.set_len(bytes_required as usize / mem::size_of::<DXGI_OUTDUPL_MOVE_RECT>())
+            };
+            return Ok(move_rects);
+        }
+    }
+}
+
+/// `GetFrameDirtyRects`/`GetFrameMoveRects` report rectangles in the coordinate space of the raw
+/// surface DXGI handed back from `AcquireNextFrame`, which is *not* rotated: when the output has
+/// a non-identity rotation, that space is transposed (and, for 180°, mirrored) relative to the
+/// upright pixel buffer `surface_to_pixel_buf` produces. Rotate `rect` the same way, so a caller
+/// can line it up with the returned pixels. `native_width`/`native_height` are the raw surface's
+/// dimensions, i.e. `output_desc.DesktopCoordinates`'s width/height with `ROTATE90`/`ROTATE270`
+/// swapped back to their pre-rotation order.
+fn rotate_rect(rect: RECT, rotation: DXGI_MODE_ROTATION, native_width: i32, native_height: i32) -> RECT {
+    match rotation {
+        DXGI_MODE_ROTATION_ROTATE90 => RECT {
+            left: native_height - rect.bottom,
+            top: rect.left,
+            right: native_height - rect.top,
+            bottom: rect.right,
+        },
+        DXGI_MODE_ROTATION_ROTATE180 => RECT {
+            left: native_width - rect.right,
+            top: native_height - rect.bottom,
+            right: native_width - rect.left,
+            bottom: native_height - rect.top,
+        },
+        DXGI_MODE_ROTATION_ROTATE270 => RECT {
+            left: rect.top,
+            top: native_width - rect.right,
+            right: rect.bottom,
+            bottom: native_width - rect.left,
+        },
+        _ => rect,
+    }
+}
+
+/// Like `rotate_rect`, but for a `DXGI_OUTDUPL_MOVE_RECT`: rotates `DestinationRect` directly,
+/// and rotates `SourcePoint` by treating it as the top-left corner of a same-sized rect in the
+/// raw coordinate space (a move preserves the size of the region it relocates).
+fn rotate_move_rect(
+    r: DXGI_OUTDUPL_MOVE_RECT,
+    rotation: DXGI_MODE_ROTATION,
+    native_width: i32,
+    native_height: i32,
+) -> MoveRect {
+    let width = r.DestinationRect.right - r.DestinationRect.left;
+    let height = r.DestinationRect.bottom - r.DestinationRect.top;
+    let source_rect = RECT {
+        left: r.SourcePoint.x,
+        top: r.SourcePoint.y,
+        right: r.SourcePoint.x + width,
+        bottom: r.SourcePoint.y + height,
+    };
+    let rotated_source = rotate_rect(source_rect, rotation, native_width, native_height);
+    MoveRect {
+        source_point: (rotated_source.left, rotated_source.top),
+        destination_rect: rotate_rect(r.DestinationRect, rotation, native_width, native_height).into(),
+    }
+}
+
+/// Fetch the new pointer shape for the currently acquired frame, resizing the query buffer and
+/// retrying if DXGI reports that it needs more space than `frame_info.PointerShapeBufferSize`
+/// suggested.
+fn get_frame_pointer_shape(
+    output_duplication: &IDXGIOutputDuplication,
+    pointer_shape_buffer_size: u32,
+) -> Result<CursorShape, HRESULT> {
+    let mut capacity = pointer_shape_buffer_size as usize;
+    loop {
+        let mut pixels: Vec<u8> = Vec::with_capacity(capacity);
+        let mut bytes_written = 0;
+        let mut shape_info = unsafe { zeroed() };
+        let hr = unsafe {
+            output_duplication.GetFramePointerShape(
+                pixels.capacity() as u32,
+                pixels.as_mut_ptr() as *mut _,
+                &mut bytes_written,
+                &mut shape_info,
+            )
+        };
+        if hr == DXGI_ERROR_MORE_DATA {
+            capacity = bytes_written as usize;
+        } else if hr_failed(hr) {
+            return Err(hr);
+        } else {
+            unsafe { pixels.set_len(bytes_written as usize) };
+            let shape_type = match shape_info.Type {
+                DXGI_OUTDUPL_POINTER_SHAPE_TYPE_MONOCHROME => PointerShapeType::Monochrome,
+                DXGI_OUTDUPL_POINTER_SHAPE_TYPE_MASKED_COLOR => PointerShapeType::MaskedColor,
+                DXGI_OUTDUPL_POINTER_SHAPE_TYPE_COLOR | _ => PointerShapeType::Color,
+            };
+            return Ok(CursorShape {
+                shape_type,
+                width: shape_info.Width,
+                height: shape_info.Height,
+                pitch: shape_info.Pitch,
+                hot_spot: (shape_info.HotSpot.x, shape_info.HotSpot.y),
+                pixels,
+            });
+        }
+    }
+}
+
+/// Alpha-blend `cursor`'s shape, if any, onto `pixel_buf` at its reported position.
+fn composite_cursor(pixel_buf: &mut [BGRA8], (width, height): (usize, usize), cursor: &CursorInfo) {
+    let shape = match (cursor.visible, cursor.shape.as_ref()) {
+        (true, Some(shape)) => shape,
+        _ => return,
+    };
+    let (cursor_x, cursor_y) = cursor.position;
+    let blend = |dst: &mut BGRA8, src: BGRA8| {
+        let a = src.a as u32;
+        let blend_channel = |d: u8, s: u8| ((s as u32 * a + d as u32 * (255 - a)) / 255) as u8;
+        *dst = BGRA8 {
+            b: blend_channel(dst.b, src.b),
+            g: blend_channel(dst.g, src.g),
+            r: blend_channel(dst.r, src.r),
+            a: 255,
+        };
+    };
+    match shape.shape_type {
+        PointerShapeType::Color => {
+            let row_pixels = shape.pitch as usize / mem::size_of::<BGRA8>();
+            for row in 0..shape.height as usize {
+                for col in 0..shape.width as usize {
+                    let (dst_x, dst_y) = (cursor_x + col as i32, cursor_y + row as i32);
+                    if dst_x < 0 || dst_y < 0 || dst_x as usize >= width || dst_y as usize >= height
+                    {
+                        continue;
+                    }
+                    let src_i = row * row_pixels + col;
+                    let src_bytes = &shape.pixels[src_i * 4..src_i * 4 + 4];
+                    let src = BGRA8 {
+                        b: src_bytes[0],
+                        g: src_bytes[1],
+                        r: src_bytes[2],
+                        a: src_bytes[3],
+                    };
+                    let dst_i = dst_y as usize * width + dst_x as usize;
+                    blend(&mut pixel_buf[dst_i], src);
+                }
+            }
+        }
+        PointerShapeType::MaskedColor => {
+            let row_pixels = shape.pitch as usize / mem::size_of::<BGRA8>();
+            for row in 0..shape.height as usize {
+                for col in 0..shape.width as usize {
+                    let (dst_x, dst_y) = (cursor_x + col as i32, cursor_y + row as i32);
+                    if dst_x < 0 || dst_y < 0 || dst_x as usize >= width || dst_y as usize >= height
+                    {
+                        continue;
+                    }
+                    let src_i = row * row_pixels + col;
+                    let src_bytes = &shape.pixels[src_i * 4..src_i * 4 + 4];
+                    let src = BGRA8 {
+                        b: src_bytes[0],
+                        g: src_bytes[1],
+                        r: src_bytes[2],
+                        a: src_bytes[3],
+                    };
+                    let dst_i = dst_y as usize * width + dst_x as usize;
+                    if src.a != 0 {
+                        // AND mask bit set: XOR the color onto the desktop
+                        let dst = &mut pixel_buf[dst_i];
+                        dst.b ^= src.b;
+                        dst.g ^= src.g;
+                        dst.r ^= src.r;
+                    } else {
+                        // AND mask bit clear: replace the desktop pixel outright
+                        pixel_buf[dst_i] = BGRA8 {
+                            b: src.b,
+                            g: src.g,
+                            r: src.r,
+                            a: 255,
+                        };
+                    }
+                }
+            }
+        }
+        PointerShapeType::Monochrome => {
+            // Mask buffer is the AND mask followed by the XOR mask, each `real_height` rows of
+            // 1bpp-packed pixels, `shape.height` really covering 2 * real_height rows.
+            let real_height = shape.height as usize / 2;
+            let get_bit = |row: usize, col: usize| {
+                let byte = shape.pixels[row * shape.pitch as usize + col / 8];
+                (byte >> (7 - (col % 8))) & 1
+            };
+            for row in 0..real_height {
+                for col in 0..shape.width as usize {
+                    let (dst_x, dst_y) = (cursor_x + col as i32, cursor_y + row as i32);
+                    if dst_x < 0 || dst_y < 0 || dst_x as usize >= width || dst_y as usize >= height
+                    {
+                        continue;
+                    }
+                    let and_bit = get_bit(row, col);
+                    let xor_bit = get_bit(real_height + row, col);
+                    let dst_i = dst_y as usize * width + dst_x as usize;
+                    match (and_bit, xor_bit) {
+                        (0, 0) => pixel_buf[dst_i] = BGRA8 { b: 0, g: 0, r: 0, a: 255 },
+                        (0, 1) => {
+                            pixel_buf[dst_i] = BGRA8 {
+                                b: 255,
+                                g: 255,
+                                r: 255,
+                                a: 255,
+                            }
+                        }
+                        (1, 0) => {}
+                        _ => {
+                            let dst = &mut pixel_buf[dst_i];
+                            dst.b = !dst.b;
+                            dst.g = !dst.g;
+                            dst.r = !dst.r;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// HLSL for the full-screen-quad vertex shader used to rotate a captured frame on the GPU. The
+/// rotation itself is baked into the per-vertex UVs computed by `rotated_quad_uvs`, so this
+/// shader just forwards clip-space position and texture coordinates.
+const ROTATE_VS_SRC: &str = "
+struct VsIn {
+    float2 pos : POSITION;
+    float2 uv : TEXCOORD0;
+};
+struct VsOut {
+    float4 pos : SV_POSITION;
+    float2 uv : TEXCOORD0;
+};
+VsOut main(VsIn input) {
+    VsOut output;
+    output.pos = float4(input.pos, 0.0, 1.0);
+    output.uv = input.uv;
+    return output;
+}
+";
+
+/// HLSL for the matching pixel shader: a plain point-sampled passthrough of the source texture.
+const ROTATE_PS_SRC: &str = "
+Texture2D src : register(t0);
+SamplerState samp : register(s0);
+float4 main(float4 pos : SV_POSITION, float2 uv : TEXCOORD0) : SV_TARGET {
+    return src.Sample(samp, uv);
+}
+";
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct Vertex {
+    pos: [f32; 2],
+    uv: [f32; 2],
+}
+
+/// Compiled shaders and GPU resources for `DuplicatedOutput::rotate_frame_via_gpu`, created
+/// lazily on first use and cached for the lifetime of the duplicated output.
+struct RotationPipeline {
+    vertex_shader: ComPtr<ID3D11VertexShader>,
+    pixel_shader: ComPtr<ID3D11PixelShader>,
+    input_layout: ComPtr<ID3D11InputLayout>,
+    sampler: ComPtr<ID3D11SamplerState>,
+    vertex_buffer: ComPtr<ID3D11Buffer>,
+}
+
+/// Compile `source`'s `entry_point` for shader model `target` (e.g. `"vs_4_0"`), returning the
+/// compiled bytecode blob.
+fn compile_hlsl(source: &str, entry_point: &str, target: &str) -> Result<ComPtr<ID3DBlob>, HRESULT> {
+    let entry_point = CString::new(entry_point).unwrap();
+    let target = CString::new(target).unwrap();
+    unsafe {
+        let mut code = ptr::null_mut();
+        let mut errors = ptr::null_mut();
+        let hr = D3DCompile(
+            source.as_ptr() as *const _,
+            source.len(),
+            ptr::null(),
+            ptr::null(),
+            ptr::null_mut(),
+            entry_point.as_ptr(),
+            target.as_ptr(),
+            D3DCOMPILE_ENABLE_STRICTNESS | D3DCOMPILE_OPTIMIZATION_LEVEL3,
+            0,
+            &mut code,
+            &mut errors,
+        );
+        if !errors.is_null() {
+            ComPtr::from_raw(errors);
+        }
+        if hr_failed(hr) {
+            return Err(hr);
+        }
+        Ok(ComPtr::from_raw(code))
+    }
+}
+
+/// Build the GPU rotation compositing pipeline: a vertex/pixel shader pair, matching input
+/// layout, a clamped point sampler, and a small dynamic vertex buffer re-filled before every
+/// draw. Returns `None` if shader compilation or resource creation fails, e.g. because the
+/// adapter's driver doesn't support the required shader model; callers fall back to the CPU
+/// rotation path in that case.
+fn create_rotation_pipeline(device: &ID3D11Device) -> Option<RotationPipeline> {
+    let vs_blob = compile_hlsl(ROTATE_VS_SRC, "main", "vs_4_0").ok()?;
+    let ps_blob = compile_hlsl(ROTATE_PS_SRC, "main", "ps_4_0").ok()?;
+
+    let vertex_shader = unsafe {
+        let (ptr, len) = (vs_blob.GetBufferPointer(), vs_blob.GetBufferSize());
+        let mut vertex_shader = ptr::null_mut();
+        let hr = device.CreateVertexShader(ptr, len, ptr::null_mut(), &mut vertex_shader);
+        if hr_failed(hr) {
+            return None;
+        }
+        ComPtr::from_raw(vertex_shader)
+    };
+    let pixel_shader = unsafe {
+        let (ptr, len) = (ps_blob.GetBufferPointer(), ps_blob.GetBufferSize());
+        let mut pixel_shader = ptr::null_mut();
+        let hr = device.CreatePixelShader(ptr, len, ptr::null_mut(), &mut pixel_shader);
+        if hr_failed(hr) {
+            return None;
+        }
+        ComPtr::from_raw(pixel_shader)
+    };
+    let position_semantic = CString::new("POSITION").unwrap();
+    let texcoord_semantic = CString::new("TEXCOORD").unwrap();
+    let input_layout = unsafe {
+        let elems = [
+            D3D11_INPUT_ELEMENT_DESC {
+                SemanticName: position_semantic.as_ptr(),
+                SemanticIndex: 0,
+                Format: DXGI_FORMAT_R32G32_FLOAT,
+                InputSlot: 0,
+                AlignedByteOffset: 0,
+                InputSlotClass: D3D11_INPUT_PER_VERTEX_DATA,
+                InstanceDataStepRate: 0,
+            },
+            D3D11_INPUT_ELEMENT_DESC {
+                SemanticName: texcoord_semantic.as_ptr(),
+                SemanticIndex: 0,
+                Format: DXGI_FORMAT_R32G32_FLOAT,
+                InputSlot: 0,
+                AlignedByteOffset: mem::size_of::<[f32; 2]>() as u32,
+                InputSlotClass: D3D11_INPUT_PER_VERTEX_DATA,
+                InstanceDataStepRate: 0,
+            },
+        ];
+        let mut input_layout = ptr::null_mut();
+        let hr = device.CreateInputLayout(
+            elems.as_ptr(),
+            elems.len() as u32,
+            vs_blob.GetBufferPointer(),
+            vs_blob.GetBufferSize(),
+            &mut input_layout,
+        );
+        if hr_failed(hr) {
+            return None;
+        }
+        ComPtr::from_raw(input_layout)
+    };
+    let sampler = unsafe {
+        let mut desc: D3D11_SAMPLER_DESC = zeroed();
+        desc.Filter = D3D11_FILTER_MIN_MAG_MIP_POINT;
+        desc.AddressU = D3D11_TEXTURE_ADDRESS_CLAMP;
+        desc.AddressV = D3D11_TEXTURE_ADDRESS_CLAMP;
+        desc.AddressW = D3D11_TEXTURE_ADDRESS_CLAMP;
+        desc.ComparisonFunc = D3D11_COMPARISON_NEVER;
+        desc.MaxLOD = D3D11_FLOAT32_MAX;
+        let mut sampler = ptr::null_mut();
+        let hr = device.CreateSamplerState(&desc, &mut sampler);
+        if hr_failed(hr) {
+            return None;
+        }
+        ComPtr::from_raw(sampler)
+    };
+    let vertex_buffer = unsafe {
+        let mut buffer_desc: D3D11_BUFFER_DESC = zeroed();
+        buffer_desc.ByteWidth = (mem::size_of::<Vertex>() * 4) as u32;
+        buffer_desc.Usage = D3D11_USAGE_DYNAMIC;
+        buffer_desc.BindFlags = D3D11_BIND_VERTEX_BUFFER;
+        buffer_desc.CPUAccessFlags = D3D11_CPU_ACCESS_WRITE;
+        let mut vertex_buffer = ptr::null_mut();
+        let hr = device.CreateBuffer(&buffer_desc, ptr::null(), &mut vertex_buffer);
+        if hr_failed(hr) {
+            return None;
+        }
+        ComPtr::from_raw(vertex_buffer)
+    };
+
+    Some(RotationPipeline {
+        vertex_shader,
+        pixel_shader,
+        input_layout,
+        sampler,
+        vertex_buffer,
+    })
+}
+
+/// UVs, in source-texture space, for the [top-left, top-right, bottom-left, bottom-right]
+/// corners of the destination quad, chosen so that sampling with these corners rotates the
+/// source by the reported amount.
+fn rotated_quad_uvs(rotation: DXGI_MODE_ROTATION) -> [[f32; 2]; 4] {
+    match rotation {
+        DXGI_MODE_ROTATION_ROTATE90 => [[0.0, 1.0], [0.0, 0.0], [1.0, 1.0], [1.0, 0.0]],
+        DXGI_MODE_ROTATION_ROTATE180 => [[1.0, 1.0], [0.0, 1.0], [1.0, 0.0], [0.0, 0.0]],
+        DXGI_MODE_ROTATION_ROTATE270 => [[1.0, 0.0], [1.0, 1.0], [0.0, 0.0], [0.0, 1.0]],
+        _ => [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 1.0]],
+    }
+}
+
 struct DuplicatedOutput {
     device: ComPtr<ID3D11Device>,
     device_context: ComPtr<ID3D11DeviceContext>,
     output: ComPtr<IDXGIOutput1>,
     output_duplication: ComPtr<IDXGIOutputDuplication>,
+    cursor_shape: Option<CursorShape>,
+    rotation_pipeline: Option<RotationPipeline>,
+    /// Cached CPU-readable staging texture(s), reused between frames for as long as the source
+    /// dimensions and format stay the same. One texture normally, two when `double_buffered`.
+    staging_textures: Vec<ComPtr<ID3D11Texture2D>>,
+    staging_texture_dims: Option<(u32, u32, DXGI_FORMAT)>,
+    staging_write_index: usize,
+    double_buffered: bool,
 }
 impl DuplicatedOutput {
     fn get_desc(&self) -> DXGI_OUTPUT_DESC {
@@ -184,10 +870,246 @@ impl DuplicatedOutput {
         }
     }
 
+    /// The pixel format DXGI actually negotiated for this output's duplicated frames, as seen on
+    /// the most recently captured frame's staging texture. `None` before the first successful
+    /// `copy_frame_to_readable_texture` call.
+    fn negotiated_format(&self) -> Option<DXGI_FORMAT> {
+        self.staging_texture_dims.map(|(_, _, format)| format)
+    }
+
+    /// Copy `frame_resource` into a CPU-readable staging texture, rotating it on the GPU first
+    /// if the output reports a non-identity rotation. The staging texture is cached on `self`
+    /// and only recreated when its dimensions or format no longer match the source, instead of
+    /// being allocated fresh every frame. Returns whether the rotation was applied, so the
+    /// caller knows whether its own CPU rotation pass is still needed.
+    ///
+    /// When `double_buffered` is set, two staging textures are cycled between and `CopyResource`
+    /// alternates which one it targets, so this frame's copy never targets the texture the
+    /// caller may still be `Map`-ing from the previous call. The texture returned is always the
+    /// one just copied into for *this* call: with only two buffers, there's no slot free to hand
+    /// back an older, already-`Map`-able capture without either redelivering a frame the caller
+    /// has already seen (if it's the one just shown) or handing back one still being written (if
+    /// it's the one about to be reused). Avoiding the write-while-mapped hazard above is the
+    /// benefit `double_buffered` actually provides; it is not a latency-hiding pipeline.
+    fn copy_frame_to_readable_texture(
+        &mut self,
+        frame_resource: &ComPtr<IDXGIResource>,
+    ) -> Result<(ComPtr<IDXGISurface1>, bool), HRESULT> {
+        let frame_texture = frame_resource.cast::<ID3D11Texture2D>().unwrap();
+        let mut texture_desc = unsafe {
+            let mut texture_desc = zeroed();
+            frame_texture.GetDesc(&mut texture_desc);
+            texture_desc
+        };
+        let rotation = self.get_desc().Rotation;
+        let rotated = match rotation {
+            DXGI_MODE_ROTATION_ROTATE90 | DXGI_MODE_ROTATION_ROTATE180
+            | DXGI_MODE_ROTATION_ROTATE270 => self.rotate_frame_via_gpu(&frame_texture, &texture_desc, rotation),
+            _ => None,
+        };
+        let (source_resource, gpu_rotated) = match rotated {
+            Some(rotated_texture) => {
+                unsafe { rotated_texture.GetDesc(&mut texture_desc) };
+                (rotated_texture.up::<ID3D11Resource>(), true)
+            }
+            None => (frame_texture.up::<ID3D11Resource>(), false),
+        };
+
+        let buffer_count = if self.double_buffered { 2 } else { 1 };
+        self.ensure_staging_textures(&texture_desc, buffer_count)?;
+
+        let write_index = self.staging_write_index;
+        let write_texture = self.staging_textures[write_index].clone();
+        unsafe {
+            self.device_context.CopyResource(
+                write_texture.up::<ID3D11Resource>().as_raw(),
+                source_resource.as_raw(),
+            );
+        }
+
+        self.staging_write_index = (write_index + 1) % buffer_count;
+        write_texture
+            .up::<ID3D11Resource>()
+            .cast()
+            .map(|surface| (surface, gpu_rotated))
+    }
+
+    /// Ensure `staging_textures` holds `count` CPU-readable textures matching `texture_desc`'s
+    /// dimensions and format, (re)creating them if they don't exist yet, the source changed
+    /// size or format (e.g. after a display mode change), or `count` changed (e.g. because
+    /// `double_buffered` was just toggled).
+    fn ensure_staging_textures(
+        &mut self,
+        texture_desc: &D3D11_TEXTURE2D_DESC,
+        count: usize,
+    ) -> Result<(), HRESULT> {
+        let dims = (texture_desc.Width, texture_desc.Height, texture_desc.Format);
+        if self.staging_texture_dims == Some(dims) && self.staging_textures.len() == count {
+            return Ok(());
+        }
+        let mut readable_desc = *texture_desc;
+        readable_desc.Usage = D3D11_USAGE_STAGING;
+        readable_desc.BindFlags = 0;
+        readable_desc.CPUAccessFlags = D3D11_CPU_ACCESS_READ;
+        readable_desc.MiscFlags = 0;
+        let mut staging_textures = Vec::with_capacity(count);
+        for _ in 0..count {
+            let readable_texture = unsafe {
+                let mut readable_texture = ptr::null_mut();
+                let hr =
+                    self.device
+                        .CreateTexture2D(&readable_desc, ptr::null(), &mut readable_texture);
+                if hr_failed(hr) {
+                    return Err(hr);
+                }
+                ComPtr::from_raw(readable_texture)
+            };
+            // Lower priorities causes stuff to be needlessly copied from gpu to ram,
+            // causing huge ram usage on some systems.
+            unsafe { readable_texture.SetEvictionPriority(DXGI_RESOURCE_PRIORITY_MAXIMUM) };
+            staging_textures.push(readable_texture);
+        }
+        self.staging_textures = staging_textures;
+        self.staging_texture_dims = Some(dims);
+        self.staging_write_index = 0;
+        Ok(())
+    }
+
+    /// Render `frame_texture` into a same-device render target sized for the rotated output,
+    /// using a full-screen-quad compositing pass, so that rotation (and in future, other
+    /// post-processing) happens on the GPU instead of via a CPU pixel transpose. Returns `None`
+    /// if the pipeline could not be created or the frame texture could not be bound as a shader
+    /// resource, in which case the caller should fall back to copying the frame unrotated.
+    fn rotate_frame_via_gpu(
+        &mut self,
+        frame_texture: &ComPtr<ID3D11Texture2D>,
+        texture_desc: &D3D11_TEXTURE2D_DESC,
+        rotation: DXGI_MODE_ROTATION,
+    ) -> Option<ComPtr<ID3D11Texture2D>> {
+        if self.rotation_pipeline.is_none() {
+            self.rotation_pipeline = create_rotation_pipeline(&self.device);
+        }
+        let pipeline = self.rotation_pipeline.as_ref()?;
+
+        let srv = unsafe {
+            let mut srv = ptr::null_mut();
+            let hr = self.device.CreateShaderResourceView(
+                frame_texture.up::<ID3D11Resource>().as_raw(),
+                ptr::null(),
+                &mut srv,
+            );
+            if hr_failed(hr) {
+                return None;
+            }
+            ComPtr::from_raw(srv)
+        };
+
+        let (out_width, out_height) = match rotation {
+            DXGI_MODE_ROTATION_ROTATE90 | DXGI_MODE_ROTATION_ROTATE270 => {
+                (texture_desc.Height, texture_desc.Width)
+            }
+            _ => (texture_desc.Width, texture_desc.Height),
+        };
+        let mut rt_desc = *texture_desc;
+        rt_desc.Width = out_width;
+        rt_desc.Height = out_height;
+        rt_desc.Usage = D3D11_USAGE_DEFAULT;
+        rt_desc.BindFlags = D3D11_BIND_RENDER_TARGET;
+        rt_desc.CPUAccessFlags = 0;
+        rt_desc.MiscFlags = 0;
+        let rt_texture = unsafe {
+            let mut rt_texture = ptr::null_mut();
+            let hr = self
+                .device
+                .CreateTexture2D(&rt_desc, ptr::null(), &mut rt_texture);
+            if hr_failed(hr) {
+                return None;
+            }
+            ComPtr::from_raw(rt_texture)
+        };
+        let rtv = unsafe {
+            let mut rtv = ptr::null_mut();
+            let hr = self.device.CreateRenderTargetView(
+                rt_texture.up::<ID3D11Resource>().as_raw(),
+                ptr::null(),
+                &mut rtv,
+            );
+            if hr_failed(hr) {
+                return None;
+            }
+            ComPtr::from_raw(rtv)
+        };
+
+        let uvs = rotated_quad_uvs(rotation);
+        let vertices = [
+            Vertex { pos: [-1.0, 1.0], uv: uvs[0] },
+            Vertex { pos: [1.0, 1.0], uv: uvs[1] },
+            Vertex { pos: [-1.0, -1.0], uv: uvs[2] },
+            Vertex { pos: [1.0, -1.0], uv: uvs[3] },
+        ];
+        unsafe {
+            let mut mapped = zeroed();
+            let hr = self.device_context.Map(
+                pipeline.vertex_buffer.up::<ID3D11Resource>().as_raw(),
+                0,
+                D3D11_MAP_WRITE_DISCARD,
+                0,
+                &mut mapped,
+            );
+            if hr_failed(hr) {
+                return None;
+            }
+            ptr::copy_nonoverlapping(vertices.as_ptr(), mapped.pData as *mut Vertex, vertices.len());
+            self.device_context
+                .Unmap(pipeline.vertex_buffer.up::<ID3D11Resource>().as_raw(), 0);
+        }
+
+        let viewport = D3D11_VIEWPORT {
+            TopLeftX: 0.0,
+            TopLeftY: 0.0,
+            Width: out_width as f32,
+            Height: out_height as f32,
+            MinDepth: 0.0,
+            MaxDepth: 1.0,
+        };
+        let stride = mem::size_of::<Vertex>() as u32;
+        let offset = 0u32;
+        unsafe {
+            self.device_context.IASetInputLayout(pipeline.input_layout.as_raw());
+            self.device_context.IASetVertexBuffers(
+                0,
+                1,
+                &pipeline.vertex_buffer.as_raw(),
+                &stride,
+                &offset,
+            );
+            self.device_context
+                .IASetPrimitiveTopology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP);
+            self.device_context
+                .VSSetShader(pipeline.vertex_shader.as_raw(), ptr::null(), 0);
+            self.device_context
+                .PSSetShader(pipeline.pixel_shader.as_raw(), ptr::null(), 0);
+            self.device_context.PSSetShaderResources(0, 1, &srv.as_raw());
+            self.device_context
+                .PSSetSamplers(0, 1, &pipeline.sampler.as_raw());
+            self.device_context.RSSetViewports(1, &viewport);
+            self.device_context
+                .OMSetRenderTargets(1, &rtv.as_raw(), ptr::null_mut());
+            self.device_context.Draw(4, 0);
+            // Unbind so the render target can be copied from / released cleanly afterwards.
+            self.device_context
+                .OMSetRenderTargets(0, ptr::null_mut(), ptr::null_mut());
+            let null_srv: *mut ID3D11ShaderResourceView = ptr::null_mut();
+            self.device_context.PSSetShaderResources(0, 1, &null_srv);
+        }
+
+        Some(rt_texture)
+    }
+
     fn capture_frame_to_surface(
         &mut self,
         timeout_ms: u32,
-    ) -> Result<ComPtr<IDXGISurface1>, HRESULT> {
+    ) -> Result<(ComPtr<IDXGISurface1>, bool), HRESULT> {
         let frame_resource = unsafe {
             let mut frame_resource = ptr::null_mut();
             let mut frame_info = zeroed();
@@ -201,39 +1123,99 @@ impl DuplicatedOutput {
             }
             ComPtr::from_raw(frame_resource)
         };
-        let frame_texture = frame_resource.cast::<ID3D11Texture2D>().unwrap();
-        let mut texture_desc = unsafe {
-            let mut texture_desc = zeroed();
-            frame_texture.GetDesc(&mut texture_desc);
-            texture_desc
-        };
-        // Configure the description to make the texture readable
-        texture_desc.Usage = D3D11_USAGE_STAGING;
-        texture_desc.BindFlags = 0;
-        texture_desc.CPUAccessFlags = D3D11_CPU_ACCESS_READ;
-        texture_desc.MiscFlags = 0;
-        let readable_texture = unsafe {
-            let mut readable_texture = ptr::null_mut();
-            let hr =
-                self.device
-                    .CreateTexture2D(&mut texture_desc, ptr::null(), &mut readable_texture);
+        let result = self.copy_frame_to_readable_texture(&frame_resource);
+        unsafe { self.output_duplication.ReleaseFrame() };
+        result
+    }
+
+    /// Like `capture_frame_to_surface`, but also returns the dirty and move rectangles reported
+    /// alongside the frame. The metadata is only valid for the frame that was just acquired, so
+    /// it must be read before `ReleaseFrame` is called.
+    fn capture_frame_to_surface_with_metadata(
+        &mut self,
+        timeout_ms: u32,
+    ) -> Result<(ComPtr<IDXGISurface1>, bool, Vec<RECT>, Vec<DXGI_OUTDUPL_MOVE_RECT>), HRESULT> {
+        let (frame_resource, frame_info) = unsafe {
+            let mut frame_resource = ptr::null_mut();
+            let mut frame_info = zeroed();
+            let hr = self.output_duplication.AcquireNextFrame(
+                timeout_ms,
+                &mut frame_info,
+                &mut frame_resource,
+            );
             if hr_failed(hr) {
                 return Err(hr);
             }
-            ComPtr::from_raw(readable_texture)
+            (ComPtr::from_raw(frame_resource), frame_info)
         };
-        // Lower priorities causes stuff to be needlessly copied from gpu to ram,
-        // causing huge ram usage on some systems.
-        unsafe { readable_texture.SetEvictionPriority(DXGI_RESOURCE_PRIORITY_MAXIMUM) };
-        let readable_surface = readable_texture.up::<ID3D11Resource>();
-        unsafe {
-            self.device_context.CopyResource(
-                readable_surface.as_raw(),
-                frame_texture.up::<ID3D11Resource>().as_raw(),
+        let (dirty_rects, move_rects) = if frame_info.TotalMetadataBufferSize > 0 {
+            let dirty_rects =
+                get_frame_dirty_rects(&self.output_duplication, frame_info.TotalMetadataBufferSize)
+                    .map_err(|hr| {
+                        unsafe { self.output_duplication.ReleaseFrame() };
+                        hr
+                    })?;
+            let move_rects =
+                get_frame_move_rects(&self.output_duplication, frame_info.TotalMetadataBufferSize)
+                    .map_err(|hr| {
+                        unsafe { self.output_duplication.ReleaseFrame() };
+                        hr
+                    })?;
+            (dirty_rects, move_rects)
+        } else {
+            (Vec::new(), Vec::new())
+        };
+        let result = self.copy_frame_to_readable_texture(&frame_resource);
+        unsafe { self.output_duplication.ReleaseFrame() };
+        result.map(|(surface, gpu_rotated)| (surface, gpu_rotated, dirty_rects, move_rects))
+    }
+
+    /// Like `capture_frame_to_surface`, but also returns the cursor position and, when DXGI has
+    /// delivered a new one, shape. The shape is cached in `self.cursor_shape` between frames
+    /// since DXGI only sends it when it changes.
+    fn capture_frame_to_surface_with_cursor(
+        &mut self,
+        timeout_ms: u32,
+    ) -> Result<(ComPtr<IDXGISurface1>, bool, CursorInfo), HRESULT> {
+        let (frame_resource, frame_info) = unsafe {
+            let mut frame_resource = ptr::null_mut();
+            let mut frame_info = zeroed();
+            let hr = self.output_duplication.AcquireNextFrame(
+                timeout_ms,
+                &mut frame_info,
+                &mut frame_resource,
             );
-            self.output_duplication.ReleaseFrame();
+            if hr_failed(hr) {
+                return Err(hr);
+            }
+            (ComPtr::from_raw(frame_resource), frame_info)
+        };
+        if frame_info.PointerShapeBufferSize > 0 {
+            match get_frame_pointer_shape(&self.output_duplication, frame_info.PointerShapeBufferSize)
+            {
+                Ok(shape) => self.cursor_shape = Some(shape),
+                Err(hr) => {
+                    unsafe { self.output_duplication.ReleaseFrame() };
+                    return Err(hr);
+                }
+            }
         }
-        readable_surface.cast()
+        // DXGI reports the cursor position in desktop-wide (virtual-screen) coordinates, but
+        // `composite_cursor` indexes into this output's own local pixel buffer, so translate to
+        // output-relative coordinates the same way `capture_combined_frame` does for its origin.
+        let RECT { left, top, .. } = self.get_desc().DesktopCoordinates;
+        let cursor_info = CursorInfo {
+            visible: frame_info.PointerPosition.Visible != 0,
+            position: (
+                frame_info.PointerPosition.Position.x - left,
+                frame_info.PointerPosition.Position.y - top,
+            ),
+            last_update_time: frame_info.LastMouseUpdateTime,
+            shape: self.cursor_shape.clone(),
+        };
+        let result = self.copy_frame_to_readable_texture(&frame_resource);
+        unsafe { self.output_duplication.ReleaseFrame() };
+        result.map(|(surface, gpu_rotated)| (surface, gpu_rotated, cursor_info))
     }
 }
 
@@ -242,6 +1224,12 @@ pub struct DXGIManager {
     duplicated_output: Option<DuplicatedOutput>,
     capture_source_index: usize,
     timeout_ms: u32,
+    composite_cursor: bool,
+    duplicate_output_retries: u32,
+    duplicate_output_retry_interval_ms: u32,
+    combined_outputs: Option<Vec<DuplicatedOutput>>,
+    hdr_output: Option<DuplicatedOutput>,
+    double_buffered_capture: bool,
 }
 
 struct SharedPtr<T>(*const T);
@@ -250,13 +1238,258 @@ unsafe impl<T> Send for SharedPtr<T> {}
 
 unsafe impl<T> Sync for SharedPtr<T> {}
 
-impl DXGIManager {
-    /// Construct a new manager with capture timeout
-    pub fn new(timeout_ms: u32) -> Result<DXGIManager, &'static str> {
-        let mut manager = DXGIManager {
-            duplicated_output: None,
+/// Map `frame_surface` and copy it into a plain `Vec`, rotating it on the CPU unless
+/// `gpu_rotated` is set, in which case it has already been rotated into its final orientation by
+/// `DuplicatedOutput::rotate_frame_via_gpu`. `output_desc` must be the `DXGI_OUTPUT_DESC` of the
+/// output `frame_surface` was captured from.
+fn surface_to_pixel_buf<T: Copy + Send + Sync + Sized>(
+    frame_surface: ComPtr<IDXGISurface1>,
+    gpu_rotated: bool,
+    output_desc: &DXGI_OUTPUT_DESC,
+) -> Result<(Vec<T>, (usize, usize)), CaptureError> {
+    let mapped_surface = unsafe {
+        let mut mapped_surface = zeroed();
+        if hr_failed(frame_surface.Map(&mut mapped_surface, DXGI_MAP_READ)) {
+            frame_surface.Release();
+            return Err(CaptureError::Fail("Failed to map surface"));
+        }
+        mapped_surface
+    };
+    let byte_size = |x| x * mem::size_of::<BGRA8>() / mem::size_of::<T>();
+    let stride = mapped_surface.Pitch as usize / mem::size_of::<BGRA8>();
+    let byte_stride = byte_size(stride);
+    let (mut output_width, mut output_height) = {
+        let RECT {
+            left,
+            top,
+            right,
+            bottom,
+        } = output_desc.DesktopCoordinates;
+        ((right - left) as usize, (bottom - top) as usize)
+    };
+    let mut pixel_buf = Vec::with_capacity(byte_size(output_width * output_height));
+
+    match output_desc.Rotation {
+        DXGI_MODE_ROTATION_ROTATE90 | DXGI_MODE_ROTATION_ROTATE270 => {
+            mem::swap(&mut output_width, &mut output_height);
+        }
+        _ => {}
+    };
+    let mapped_pixels = unsafe {
+        slice::from_raw_parts(
+            mapped_surface.pBits as *const T,
+            byte_stride * output_height,
+        )
+    };
+    match if gpu_rotated {
+        DXGI_MODE_ROTATION_IDENTITY
+    } else {
+        output_desc.Rotation
+    } {
+        DXGI_MODE_ROTATION_IDENTITY | DXGI_MODE_ROTATION_UNSPECIFIED =>
+            pixel_buf.extend_from_slice(mapped_pixels),
+        DXGI_MODE_ROTATION_ROTATE90 => {
+            unsafe {
+                let mut buf = Vec::new();
+                mem::swap(&mut pixel_buf, &mut buf);
+                let len = buf.capacity();
+                let ptr = SharedPtr(buf.as_ptr() as *const BGRA8);
+                mapped_pixels.chunks(byte_stride).rev().enumerate().for_each(|(column, chunk)| {
+                    let mut src = chunk.as_ptr() as *const BGRA8;
+                    let mut dst = ptr.0 as *mut BGRA8;
+                    dst = dst.add(column);
+                    let stop = src.add(output_height);
+                    while src != stop {
+                        dst.write(*src);
+                        src = src.add(1);
+                        dst = dst.add(output_width);
+                    }
+                });
+                pixel_buf = Vec::from_raw_parts(buf.as_mut_ptr(), len, len);
+                mem::forget(buf);
+            }
+        }
+        DXGI_MODE_ROTATION_ROTATE180 => {
+            unsafe {
+                let mut buf = Vec::new();
+                mem::swap(&mut pixel_buf, &mut buf);
+                let len = buf.capacity();
+                let ptr = SharedPtr(buf.as_ptr() as *const BGRA8);
+                mapped_pixels.chunks(byte_stride).rev().enumerate().for_each(|(scan_line, chunk)| {
+                    let mut src = chunk.as_ptr() as *const BGRA8;
+                    let mut dst = ptr.0 as *mut BGRA8;
+                    dst = dst.add(scan_line * output_width);
+                    let stop = src;
+                    src = src.add(output_width);
+                    while src != stop {
+                        src = src.sub(1);
+                        dst.write(*src);
+                        dst = dst.add(1);
+                    }
+                });
+                pixel_buf = Vec::from_raw_parts(buf.as_mut_ptr(), len, len);
+                mem::forget(buf);
+            }
+        }
+        DXGI_MODE_ROTATION_ROTATE270 => {
+            unsafe {
+                let mut buf = Vec::new();
+                mem::swap(&mut pixel_buf, &mut buf);
+                let len = buf.capacity();
+                let ptr = SharedPtr(buf.as_ptr() as *const BGRA8);
+                mapped_pixels.chunks(byte_stride).enumerate().for_each(|(column, chunk)| {
+                    let mut src = chunk.as_ptr() as *const BGRA8;
+                    let mut dst = ptr.0 as *mut BGRA8;
+                    dst = dst.add(column);
+                    let stop = src;
+                    src = src.add(output_height);
+                    while src != stop {
+                        src = src.sub(1);
+                        dst.write(*src);
+                        dst = dst.add(output_width);
+                    }
+                });
+                pixel_buf = Vec::from_raw_parts(buf.as_mut_ptr(), len, len);
+                mem::forget(buf);
+            }
+        }
+        _ => unimplemented!(),
+    }
+    unsafe { frame_surface.Unmap() };
+    Ok((pixel_buf, (output_width, output_height)))
+}
+
+/// Like `surface_to_pixel_buf`, but for an HDR surface acquired via `DuplicateOutput1`, whose
+/// native pixel format is `RGBA16F` (8 bytes per pixel) rather than the 4-byte `BGRA8` the rest
+/// of the crate assumes.
+fn surface_to_pixel_buf_f16(
+    frame_surface: ComPtr<IDXGISurface1>,
+    gpu_rotated: bool,
+    output_desc: &DXGI_OUTPUT_DESC,
+) -> Result<(Vec<RGBA16F>, (usize, usize)), CaptureError> {
+    let mapped_surface = unsafe {
+        let mut mapped_surface = zeroed();
+        if hr_failed(frame_surface.Map(&mut mapped_surface, DXGI_MAP_READ)) {
+            frame_surface.Release();
+            return Err(CaptureError::Fail("Failed to map surface"));
+        }
+        mapped_surface
+    };
+    let stride = mapped_surface.Pitch as usize / mem::size_of::<RGBA16F>();
+    let (mut output_width, mut output_height) = {
+        let RECT {
+            left,
+            top,
+            right,
+            bottom,
+        } = output_desc.DesktopCoordinates;
+        ((right - left) as usize, (bottom - top) as usize)
+    };
+    let mut pixel_buf = Vec::with_capacity(output_width * output_height);
+
+    match output_desc.Rotation {
+        DXGI_MODE_ROTATION_ROTATE90 | DXGI_MODE_ROTATION_ROTATE270 => {
+            mem::swap(&mut output_width, &mut output_height);
+        }
+        _ => {}
+    };
+    let mapped_pixels = unsafe {
+        slice::from_raw_parts(
+            mapped_surface.pBits as *const RGBA16F,
+            stride * output_height,
+        )
+    };
+    match if gpu_rotated {
+        DXGI_MODE_ROTATION_IDENTITY
+    } else {
+        output_desc.Rotation
+    } {
+        DXGI_MODE_ROTATION_IDENTITY | DXGI_MODE_ROTATION_UNSPECIFIED =>
+            pixel_buf.extend_from_slice(mapped_pixels),
+        DXGI_MODE_ROTATION_ROTATE90 => {
+            unsafe {
+                let mut buf = Vec::new();
+                mem::swap(&mut pixel_buf, &mut buf);
+                let len = buf.capacity();
+                let ptr = SharedPtr(buf.as_ptr() as *const RGBA16F);
+                mapped_pixels.chunks(stride).rev().enumerate().for_each(|(column, chunk)| {
+                    let mut src = chunk.as_ptr() as *const RGBA16F;
+                    let mut dst = ptr.0 as *mut RGBA16F;
+                    dst = dst.add(column);
+                    let stop = src.add(output_height);
+                    while src != stop {
+                        dst.write(*src);
+                        src = src.add(1);
+                        dst = dst.add(output_width);
+                    }
+                });
+                pixel_buf = Vec::from_raw_parts(buf.as_mut_ptr(), len, len);
+                mem::forget(buf);
+            }
+        }
+        DXGI_MODE_ROTATION_ROTATE180 => {
+            unsafe {
+                let mut buf = Vec::new();
+                mem::swap(&mut pixel_buf, &mut buf);
+                let len = buf.capacity();
+                let ptr = SharedPtr(buf.as_ptr() as *const RGBA16F);
+                mapped_pixels.chunks(stride).rev().enumerate().for_each(|(scan_line, chunk)| {
+                    let mut src = chunk.as_ptr() as *const RGBA16F;
+                    let mut dst = ptr.0 as *mut RGBA16F;
+                    dst = dst.add(scan_line * output_width);
+                    let stop = src;
+                    src = src.add(output_width);
+                    while src != stop {
+                        src = src.sub(1);
+                        dst.write(*src);
+                        dst = dst.add(1);
+                    }
+                });
+                pixel_buf = Vec::from_raw_parts(buf.as_mut_ptr(), len, len);
+                mem::forget(buf);
+            }
+        }
+        DXGI_MODE_ROTATION_ROTATE270 => {
+            unsafe {
+                let mut buf = Vec::new();
+                mem::swap(&mut pixel_buf, &mut buf);
+                let len = buf.capacity();
+                let ptr = SharedPtr(buf.as_ptr() as *const RGBA16F);
+                mapped_pixels.chunks(stride).enumerate().for_each(|(column, chunk)| {
+                    let mut src = chunk.as_ptr() as *const RGBA16F;
+                    let mut dst = ptr.0 as *mut RGBA16F;
+                    dst = dst.add(column);
+                    let stop = src;
+                    src = src.add(output_height);
+                    while src != stop {
+                        src = src.sub(1);
+                        dst.write(*src);
+                        dst = dst.add(output_width);
+                    }
+                });
+                pixel_buf = Vec::from_raw_parts(buf.as_mut_ptr(), len, len);
+                mem::forget(buf);
+            }
+        }
+        _ => unimplemented!(),
+    }
+    unsafe { frame_surface.Unmap() };
+    Ok((pixel_buf, (output_width, output_height)))
+}
+
+impl DXGIManager {
+    /// Construct a new manager with capture timeout
+    pub fn new(timeout_ms: u32) -> Result<DXGIManager, &'static str> {
+        let mut manager = DXGIManager {
+            duplicated_output: None,
             capture_source_index: 0,
             timeout_ms: timeout_ms,
+            composite_cursor: false,
+            duplicate_output_retries: DEFAULT_DUPLICATE_OUTPUT_RETRIES,
+            duplicate_output_retry_interval_ms: DEFAULT_DUPLICATE_OUTPUT_RETRY_INTERVAL_MS,
+            combined_outputs: None,
+            hdr_output: None,
+            double_buffered_capture: false,
         };
 
         match manager.acquire_output_duplication() {
@@ -280,9 +1513,35 @@ impl DXGIManager {
         self.timeout_ms = timeout_ms
     }
 
+    /// Set whether `capture_frame_with_cursor` should alpha-blend the cursor into the returned
+    /// buffer, rather than leaving the caller to draw it from the returned `CursorInfo`.
+    pub fn set_composite_cursor(&mut self, composite_cursor: bool) {
+        self.composite_cursor = composite_cursor
+    }
+
+    /// Set whether to cycle staging-texture readback across two buffers instead of reusing one:
+    /// each frame's `CopyResource` is issued into the texture the *other* buffer isn't currently
+    /// holding, so if the caller is still `Map`-ing the surface returned by the previous call when
+    /// the next frame arrives, that `CopyResource` doesn't target a texture the CPU still has
+    /// mapped. With a single buffer, holding onto a returned surface across calls risks exactly
+    /// that. This doesn't defer delivery or add latency: the surface returned is always the one
+    /// just copied into for that call. Takes effect the next time the output duplication is
+    /// (re)acquired.
+    pub fn set_double_buffered_capture(&mut self, double_buffered: bool) {
+        self.double_buffered_capture = double_buffered
+    }
+
+    /// Set how many times in total to attempt `DuplicateOutput` and how long to wait between
+    /// attempts before giving up. Defaults to 10 attempts, 50 ms apart.
+    pub fn set_duplicate_output_retry(&mut self, retries: u32, interval_ms: u32) {
+        self.duplicate_output_retries = retries;
+        self.duplicate_output_retry_interval_ms = interval_ms;
+    }
+
     /// Duplicate and acquire output selected by `capture_source_index`
     pub fn acquire_output_duplication(&mut self) -> Result<(), ()> {
         self.duplicated_output = None;
+        attach_thread_to_input_desktop();
         let factory = create_dxgi_factory_1();
         for (outputs, adapter) in (0..)
             .map(|i| {
@@ -302,8 +1561,13 @@ impl DXGIManager {
         {
             // Creating device for each adapter that has the output
             let (d3d11_device, device_context) = d3d11_create_device(adapter.up().as_raw());
-            let (d3d11_device, output_duplications) =
-                duplicate_outputs(d3d11_device, outputs).map_err(|_| ())?;
+            let (d3d11_device, output_duplications) = duplicate_outputs(
+                d3d11_device,
+                outputs,
+                self.duplicate_output_retries,
+                self.duplicate_output_retry_interval_ms,
+            )
+            .map_err(|_| ())?;
             if let Some((output_duplication, output)) =
                 get_capture_source(output_duplications, self.capture_source_index)
             {
@@ -312,6 +1576,12 @@ impl DXGIManager {
                     device_context: device_context,
                     output: output,
                     output_duplication: output_duplication,
+                    cursor_shape: None,
+                    rotation_pipeline: None,
+                    staging_textures: Vec::new(),
+                    staging_texture_dims: None,
+                    staging_write_index: 0,
+                    double_buffered: self.double_buffered_capture,
                 });
                 return Ok(());
             }
@@ -319,7 +1589,113 @@ impl DXGIManager {
         Err(())
     }
 
-    fn capture_frame_to_surface(&mut self) -> Result<ComPtr<IDXGISurface1>, CaptureError> {
+    /// Duplicate and acquire every attached output across every adapter, for
+    /// `capture_combined_frame`. Unlike `acquire_output_duplication`, all outputs are kept
+    /// rather than narrowing down to the one selected by `capture_source_index`.
+    fn acquire_combined_output_duplication(&mut self) -> Result<(), ()> {
+        self.combined_outputs = None;
+        attach_thread_to_input_desktop();
+        let factory = create_dxgi_factory_1();
+        let mut combined_outputs = Vec::new();
+        for (outputs, adapter) in (0..)
+            .map(|i| {
+                let mut adapter = ptr::null_mut();
+                unsafe {
+                    if factory.EnumAdapters1(i, &mut adapter) != DXGI_ERROR_NOT_FOUND {
+                        Some(ComPtr::from_raw(adapter))
+                    } else {
+                        None
+                    }
+                }
+            })
+            .take_while(Option::is_some)
+            .map(Option::unwrap)
+            .map(|mut adapter| (get_adapter_outputs(&mut adapter), adapter))
+            .filter(|&(ref outs, _)| !outs.is_empty())
+        {
+            // Creating device for each adapter that has the output
+            let (d3d11_device, device_context) = d3d11_create_device(adapter.up().as_raw());
+            let (d3d11_device, output_duplications) = duplicate_outputs(
+                d3d11_device,
+                outputs,
+                self.duplicate_output_retries,
+                self.duplicate_output_retry_interval_ms,
+            )
+            .map_err(|_| ())?;
+            for (output_duplication, output) in output_duplications {
+                combined_outputs.push(DuplicatedOutput {
+                    device: d3d11_device.clone(),
+                    device_context: device_context.clone(),
+                    output: output,
+                    output_duplication: output_duplication,
+                    cursor_shape: None,
+                    rotation_pipeline: None,
+                    staging_textures: Vec::new(),
+                    staging_texture_dims: None,
+                    staging_write_index: 0,
+                    double_buffered: self.double_buffered_capture,
+                });
+            }
+        }
+        if combined_outputs.is_empty() {
+            return Err(());
+        }
+        self.combined_outputs = Some(combined_outputs);
+        Ok(())
+    }
+
+    /// Duplicate and acquire the output selected by `capture_source_index` for HDR capture, via
+    /// `IDXGIOutput5::DuplicateOutput1`, for use by `capture_frame_f16`.
+    fn acquire_hdr_output_duplication(&mut self) -> Result<(), ()> {
+        self.hdr_output = None;
+        attach_thread_to_input_desktop();
+        let factory = create_dxgi_factory_1();
+        for (outputs, adapter) in (0..)
+            .map(|i| {
+                let mut adapter = ptr::null_mut();
+                unsafe {
+                    if factory.EnumAdapters1(i, &mut adapter) != DXGI_ERROR_NOT_FOUND {
+                        Some(ComPtr::from_raw(adapter))
+                    } else {
+                        None
+                    }
+                }
+            })
+            .take_while(Option::is_some)
+            .map(Option::unwrap)
+            .map(|mut adapter| (get_adapter_outputs(&mut adapter), adapter))
+            .filter(|&(ref outs, _)| !outs.is_empty())
+        {
+            let (d3d11_device, device_context) = d3d11_create_device(adapter.up().as_raw());
+            let (d3d11_device, output_duplications) = duplicate_outputs_hdr(
+                d3d11_device,
+                outputs,
+                self.duplicate_output_retries,
+                self.duplicate_output_retry_interval_ms,
+            )
+            .map_err(|_| ())?;
+            if let Some((output_duplication, output)) =
+                get_capture_source(output_duplications, self.capture_source_index)
+            {
+                self.hdr_output = Some(DuplicatedOutput {
+                    device: d3d11_device,
+                    device_context: device_context,
+                    output: output,
+                    output_duplication: output_duplication,
+                    cursor_shape: None,
+                    rotation_pipeline: None,
+                    staging_textures: Vec::new(),
+                    staging_texture_dims: None,
+                    staging_write_index: 0,
+                    double_buffered: self.double_buffered_capture,
+                });
+                return Ok(());
+            }
+        }
+        Err(())
+    }
+
+    fn capture_frame_to_surface(&mut self) -> Result<(ComPtr<IDXGISurface1>, bool), CaptureError> {
         if let None = self.duplicated_output {
             if let Ok(_) = self.acquire_output_duplication() {
                 return Err(CaptureError::Fail("No valid duplicated output"));
@@ -354,140 +1730,151 @@ impl DXGIManager {
         }
     }
 
-    fn capture_frame_t<T: Copy + Send + Sync + Sized>(&mut self) -> Result<(Vec<T>, (usize, usize)), CaptureError> {
-        let frame_surface = match self.capture_frame_to_surface() {
-            Ok(surface) => surface,
-            Err(e) => return Err(e),
-        };
-        let mapped_surface = unsafe {
-            let mut mapped_surface = zeroed();
-            if hr_failed(frame_surface.Map(&mut mapped_surface, DXGI_MAP_READ)) {
-                frame_surface.Release();
-                return Err(CaptureError::Fail("Failed to map surface"));
+    /// Capture a frame from the duplicated output, returning the acquired surface together with
+    /// its dirty and move rectangles. On `DXGI_ERROR_ACCESS_LOST` the output duplication is
+    /// re-acquired and `CaptureError::AccessLost` is returned; the capture itself is not retried.
+    fn capture_frame_to_surface_with_metadata(
+        &mut self,
+    ) -> Result<(ComPtr<IDXGISurface1>, bool, Vec<RECT>, Vec<DXGI_OUTDUPL_MOVE_RECT>), CaptureError> {
+        if let None = self.duplicated_output {
+            if let Ok(_) = self.acquire_output_duplication() {
+                return Err(CaptureError::Fail("No valid duplicated output"));
+            } else {
+                return Err(CaptureError::RefreshFailure);
             }
-            mapped_surface
-        };
-        let byte_size = |x| x * mem::size_of::<BGRA8>() / mem::size_of::<T>();
-        let output_desc = self.duplicated_output.as_mut().unwrap().get_desc();
-        let stride = mapped_surface.Pitch as usize / mem::size_of::<BGRA8>();
-        let byte_stride = byte_size(stride);
-        let (mut output_width, mut output_height) = {
-            let RECT {
-                left,
-                top,
-                right,
-                bottom,
-            } = output_desc.DesktopCoordinates;
-            ((right - left) as usize, (bottom - top) as usize)
-        };
-        let mut pixel_buf = Vec::with_capacity(byte_size(output_width * output_height));
-        
-        match output_desc.Rotation {
-            DXGI_MODE_ROTATION_ROTATE90 | DXGI_MODE_ROTATION_ROTATE270 => {
-                mem::swap(&mut output_width, &mut output_height);
+        }
+        let timeout_ms = self.timeout_ms;
+        match self
+            .duplicated_output
+            .as_mut()
+            .unwrap()
+            .capture_frame_to_surface_with_metadata(timeout_ms)
+        {
+            Ok(surface_and_rects) => Ok(surface_and_rects),
+            Err(DXGI_ERROR_ACCESS_LOST) => {
+                if let Ok(_) = self.acquire_output_duplication() {
+                    Err(CaptureError::AccessLost)
+                } else {
+                    Err(CaptureError::RefreshFailure)
+                }
             }
-            _ => {}
-        };
-        // let pixel_index: Box<dyn Fn(usize, usize) -> usize> = match output_desc.Rotation {
-        //     DXGI_MODE_ROTATION_IDENTITY | DXGI_MODE_ROTATION_UNSPECIFIED => {
-        //         Box::new(|row, col| row * map_pitch_n_pixels + col)
-        //     }
-        //     DXGI_MODE_ROTATION_ROTATE90 => {
-        //         Box::new(|row, col| (output_width - 1 - col) * map_pitch_n_pixels + row)
-        //     }
-        //     DXGI_MODE_ROTATION_ROTATE180 => Box::new(|row, col| {
-        //         (output_height - 1 - row) * map_pitch_n_pixels + (output_width - col - 1)
-        //     }),
-        //     DXGI_MODE_ROTATION_ROTATE270 => {
-        //         Box::new(|row, col| col * map_pitch_n_pixels + (output_height - row - 1))
-        //     }
-        //     n => unreachable!("Undefined DXGI_MODE_ROTATION: {}", n),
-        // };
-        let mapped_pixels = unsafe {
-            slice::from_raw_parts(
-                mapped_surface.pBits as *const T,
-                byte_stride * output_height,
-            )
-        };
-        // for row in 0..output_height {
-        //     for col in 0..output_width {
-        //         pixel_buf.push(mapped_pixels[row * map_pitch_n_pixels + col]);
-        //     }
-        // }
-        let now = Instant::now();
-        match output_desc.Rotation {
-            DXGI_MODE_ROTATION_IDENTITY | DXGI_MODE_ROTATION_UNSPECIFIED =>
-                pixel_buf.extend_from_slice(mapped_pixels),
-            DXGI_MODE_ROTATION_ROTATE90 => {
-                unsafe {
-                    let mut buf = Vec::new();
-                    mem::swap(&mut pixel_buf, &mut buf);
-                    let len = buf.capacity();
-                    let ptr = SharedPtr(buf.as_ptr() as *const BGRA8);
-                    mapped_pixels.chunks(byte_stride).rev().enumerate().for_each(|(column, chunk)| {
-                        let mut src = chunk.as_ptr() as *const BGRA8;
-                        let mut dst = ptr.0 as *mut BGRA8;
-                        dst = dst.add(column);
-                        let stop = src.add(output_height);
-                        while src != stop {
-                            dst.write(*src);
-                            src = src.add(1);
-                            dst = dst.add(output_width);
-                        }
-                    });
-                    pixel_buf = Vec::from_raw_parts(buf.as_mut_ptr(), len, len);
-                    mem::forget(buf);
+            Err(E_ACCESSDENIED) => Err(CaptureError::AccessDenied),
+            Err(DXGI_ERROR_WAIT_TIMEOUT) => Err(CaptureError::Timeout),
+            Err(_) => {
+                if let Ok(_) = self.acquire_output_duplication() {
+                    Err(CaptureError::Fail("Failure when acquiring frame"))
+                } else {
+                    Err(CaptureError::RefreshFailure)
                 }
             }
-            DXGI_MODE_ROTATION_ROTATE180 => {
-                unsafe {
-                    let mut buf = Vec::new();
-                    mem::swap(&mut pixel_buf, &mut buf);
-                    let len = buf.capacity();
-                    let ptr = SharedPtr(buf.as_ptr() as *const BGRA8);
-                    mapped_pixels.chunks(byte_stride).rev().enumerate().for_each(|(scan_line, chunk)| {
-                        let mut src = chunk.as_ptr() as *const BGRA8;
-                        let mut dst = ptr.0 as *mut BGRA8;
-                        dst = dst.add(scan_line * output_width);
-                        let stop = src;
-                        src = src.add(output_width);
-                        while src != stop {
-                            src = src.sub(1);
-                            dst.write(*src);
-                            dst = dst.add(1);
-                        }
-                    });
-                    pixel_buf = Vec::from_raw_parts(buf.as_mut_ptr(), len, len);
-                    mem::forget(buf);
+        }
+    }
+
+    /// Capture a frame from the duplicated output, returning the acquired surface together with
+    /// the cursor position and, if changed, shape. On `DXGI_ERROR_ACCESS_LOST` the output
+    /// duplication is re-acquired and `CaptureError::AccessLost` is returned; the capture itself
+    /// is not retried.
+    fn capture_frame_to_surface_with_cursor(
+        &mut self,
+    ) -> Result<(ComPtr<IDXGISurface1>, bool, CursorInfo), CaptureError> {
+        if let None = self.duplicated_output {
+            if let Ok(_) = self.acquire_output_duplication() {
+                return Err(CaptureError::Fail("No valid duplicated output"));
+            } else {
+                return Err(CaptureError::RefreshFailure);
+            }
+        }
+        let timeout_ms = self.timeout_ms;
+        match self
+            .duplicated_output
+            .as_mut()
+            .unwrap()
+            .capture_frame_to_surface_with_cursor(timeout_ms)
+        {
+            Ok(surface_and_cursor) => Ok(surface_and_cursor),
+            Err(DXGI_ERROR_ACCESS_LOST) => {
+                if let Ok(_) = self.acquire_output_duplication() {
+                    Err(CaptureError::AccessLost)
+                } else {
+                    Err(CaptureError::RefreshFailure)
                 }
             }
-            DXGI_MODE_ROTATION_ROTATE270 => {
-                unsafe {
-                    let mut buf = Vec::new();
-                    mem::swap(&mut pixel_buf, &mut buf);
-                    let len = buf.capacity();
-                    let ptr = SharedPtr(buf.as_ptr() as *const BGRA8);
-                    mapped_pixels.chunks(byte_stride).enumerate().for_each(|(column, chunk)| {
-                        let mut src = chunk.as_ptr() as *const BGRA8;
-                        let mut dst = ptr.0 as *mut BGRA8;
-                        dst = dst.add(column);
-                        let stop = src;
-                        src = src.add(output_height);
-                        while src != stop {
-                            src = src.sub(1);
-                            dst.write(*src);
-                            dst = dst.add(output_width);
-                        }
-                    });
-                    pixel_buf = Vec::from_raw_parts(buf.as_mut_ptr(), len, len);
-                    mem::forget(buf);
+            Err(E_ACCESSDENIED) => Err(CaptureError::AccessDenied),
+            Err(DXGI_ERROR_WAIT_TIMEOUT) => Err(CaptureError::Timeout),
+            Err(_) => {
+                if let Ok(_) = self.acquire_output_duplication() {
+                    Err(CaptureError::Fail("Failure when acquiring frame"))
+                } else {
+                    Err(CaptureError::RefreshFailure)
                 }
             }
-            _ => unimplemented!(),
         }
-        dbg!(Instant::now() - now);
-        unsafe { frame_surface.Unmap() };
-        Ok((pixel_buf, (output_width, output_height)))
+    }
+
+    fn capture_frame_t<T: Copy + Send + Sync + Sized>(&mut self) -> Result<(Vec<T>, (usize, usize)), CaptureError> {
+        let (frame_surface, gpu_rotated) = match self.capture_frame_to_surface() {
+            Ok(surface) => surface,
+            Err(e) => return Err(e),
+        };
+        self.surface_to_pixel_buf_t(frame_surface, gpu_rotated)
+    }
+
+    /// Like `capture_frame`, but also returns the `CursorInfo` for the captured frame, and, if
+    /// `composite_cursor` was set, blends the cursor into the returned pixels.
+    fn capture_frame_cursor_t(
+        &mut self,
+    ) -> Result<(Vec<BGRA8>, (usize, usize), CursorInfo), CaptureError> {
+        let (frame_surface, gpu_rotated, cursor_info) = self.capture_frame_to_surface_with_cursor()?;
+        let (mut pixel_buf, dims) = self.surface_to_pixel_buf_t(frame_surface, gpu_rotated)?;
+        if self.composite_cursor {
+            composite_cursor(&mut pixel_buf, dims, &cursor_info);
+        }
+        Ok((pixel_buf, dims, cursor_info))
+    }
+
+    /// Like `capture_frame_t`, but also returns the dirty and move rectangles for the captured
+    /// frame.
+    fn capture_frame_metadata_t<T: Copy + Send + Sync + Sized>(
+        &mut self,
+    ) -> Result<(Vec<T>, (usize, usize), Vec<DirtyRect>, Vec<MoveRect>), CaptureError> {
+        let (frame_surface, gpu_rotated, dirty_rects, move_rects) =
+            match self.capture_frame_to_surface_with_metadata() {
+                Ok(surface_and_rects) => surface_and_rects,
+                Err(e) => return Err(e),
+            };
+        let output_desc = self.duplicated_output.as_ref().unwrap().get_desc();
+        let (pixel_buf, dims) = self.surface_to_pixel_buf_t(frame_surface, gpu_rotated)?;
+        // Dirty/move rects are reported in the raw, pre-rotation surface's coordinate space
+        // regardless of `gpu_rotated`; rotate them to match `pixel_buf`'s orientation.
+        let rotation = output_desc.Rotation;
+        let (mut native_width, mut native_height) = {
+            let RECT { left, top, right, bottom } = output_desc.DesktopCoordinates;
+            (right - left, bottom - top)
+        };
+        if let DXGI_MODE_ROTATION_ROTATE90 | DXGI_MODE_ROTATION_ROTATE270 = rotation {
+            mem::swap(&mut native_width, &mut native_height);
+        }
+        let dirty_rects = dirty_rects
+            .into_iter()
+            .map(|r| DirtyRect::from(rotate_rect(r, rotation, native_width, native_height)))
+            .collect();
+        let move_rects = move_rects
+            .into_iter()
+            .map(|r| rotate_move_rect(r, rotation, native_width, native_height))
+            .collect();
+        Ok((pixel_buf, dims, dirty_rects, move_rects))
+    }
+
+    /// Map `frame_surface` and copy it into a plain `Vec`, rotating it on the CPU unless
+    /// `gpu_rotated` is set, in which case it has already been rotated into its final
+    /// orientation by `DuplicatedOutput::rotate_frame_via_gpu`.
+    fn surface_to_pixel_buf_t<T: Copy + Send + Sync + Sized>(
+        &mut self,
+        frame_surface: ComPtr<IDXGISurface1>,
+        gpu_rotated: bool,
+    ) -> Result<(Vec<T>, (usize, usize)), CaptureError> {
+        let output_desc = self.duplicated_output.as_mut().unwrap().get_desc();
+        surface_to_pixel_buf(frame_surface, gpu_rotated, &output_desc)
     }
 
     /// Capture a frame
@@ -505,9 +1892,184 @@ impl DXGIManager {
     pub fn capture_frame_components(&mut self) -> Result<(Vec<u8>, (usize, usize)), CaptureError> {
         self.capture_frame_t()
     }
+
+    /// Capture a frame along with the dirty and move rectangles DXGI reports for it.
+    ///
+    /// A dirty rectangle is a region whose contents changed since the previous frame. A move
+    /// rectangle is a region that was simply translated from elsewhere in the previous frame,
+    /// e.g. by dragging a window. A caller that tracks frames itself can use these to update only
+    /// the parts of its own buffer that actually changed, rather than re-processing the whole
+    /// frame.
+    ///
+    /// On success, return Vec with pixels, width and height of frame, dirty rectangles and move
+    /// rectangles. On failure, return CaptureError.
+    pub fn capture_frame_with_metadata(
+        &mut self,
+    ) -> Result<(Vec<BGRA8>, (usize, usize), Vec<DirtyRect>, Vec<MoveRect>), CaptureError> {
+        self.capture_frame_metadata_t()
+    }
+
+    /// Capture a frame along with the current cursor position and shape.
+    ///
+    /// DXGI only delivers a new shape when it actually changes, so `CursorInfo::shape` is `None`
+    /// until the first shape update arrives, and otherwise repeats the last shape seen. When
+    /// `set_composite_cursor(true)` has been called, the cursor is alpha-blended into the
+    /// returned pixels at its reported position.
+    ///
+    /// On success, return Vec with pixels, width and height of frame, and cursor info. On
+    /// failure, return CaptureError.
+    pub fn capture_frame_with_cursor(
+        &mut self,
+    ) -> Result<(Vec<BGRA8>, (usize, usize), CursorInfo), CaptureError> {
+        self.capture_frame_cursor_t()
+    }
+
+    /// Capture every attached output, across every adapter, and stitch them into a single frame
+    /// spanning the whole virtual desktop.
+    ///
+    /// Each output is placed according to its `DXGI_OUTPUT_DESC::DesktopCoordinates`, relative to
+    /// the bounding box of all outputs' coordinates, which is also the size of the returned
+    /// frame. An output that times out or loses access while the others succeed does not fail the
+    /// whole capture; its region of the returned buffer is simply left unwritten for that frame.
+    ///
+    /// On success, return Vec with pixels and width and height of the combined frame. On failure
+    /// to acquire any output at all, return CaptureError.
+    pub fn capture_combined_frame(&mut self) -> Result<(Vec<BGRA8>, (usize, usize)), CaptureError> {
+        if self.combined_outputs.is_none() {
+            self.acquire_combined_output_duplication()
+                .map_err(|_| CaptureError::RefreshFailure)?;
+        }
+        let outputs = self.combined_outputs.as_mut().unwrap();
+        let descs: Vec<DXGI_OUTPUT_DESC> = outputs.iter().map(|o| o.get_desc()).collect();
+        let (bound_left, bound_top, bound_right, bound_bottom) = descs.iter().fold(
+            (i32::max_value(), i32::max_value(), i32::min_value(), i32::min_value()),
+            |(l, t, r, b), desc| {
+                let RECT {
+                    left,
+                    top,
+                    right,
+                    bottom,
+                } = desc.DesktopCoordinates;
+                (l.min(left), t.min(top), r.max(right), b.max(bottom))
+            },
+        );
+        let width = (bound_right - bound_left) as usize;
+        let height = (bound_bottom - bound_top) as usize;
+        let mut combined = vec![
+            BGRA8 {
+                b: 0,
+                g: 0,
+                r: 0,
+                a: 0
+            };
+            width * height
+        ];
+
+        let timeout_ms = self.timeout_ms;
+        // A failure here just leaves that output's region blank for this frame; if the failure
+        // is persistent (e.g. access lost after a mode change), drop `combined_outputs` so the
+        // next call re-duplicates every output from scratch instead of leaving the region
+        // permanently blank, mirroring how `acquire_output_duplication` recovers in the
+        // single-output path. `DXGI_ERROR_WAIT_TIMEOUT` (nothing changed on that output since the
+        // last frame) and `E_ACCESSDENIED` (e.g. protected fullscreen content) are routine and
+        // don't warrant reacquiring every output, same as `capture_frame_to_surface` above treats
+        // them as non-fatal rather than triggering `acquire_output_duplication`.
+        let mut needs_reacquire = false;
+        for (output, desc) in outputs.iter_mut().zip(descs.iter()) {
+            let (frame_surface, gpu_rotated) = match output.capture_frame_to_surface(timeout_ms) {
+                Ok(result) => result,
+                Err(DXGI_ERROR_WAIT_TIMEOUT) | Err(E_ACCESSDENIED) => continue,
+                Err(_) => {
+                    needs_reacquire = true;
+                    continue;
+                }
+            };
+            let (pixels, (out_width, out_height)) =
+                match surface_to_pixel_buf::<BGRA8>(frame_surface, gpu_rotated, desc) {
+                    Ok(result) => result,
+                    Err(_) => {
+                        needs_reacquire = true;
+                        continue;
+                    }
+                };
+            let RECT { left, top, .. } = desc.DesktopCoordinates;
+            let origin_x = (left - bound_left) as usize;
+            let origin_y = (top - bound_top) as usize;
+            // An unrotated buffer keeps the mapped surface's row pitch, which may exceed the width.
+            let src_stride = pixels.len() / out_height;
+            for row in 0..out_height {
+                let src = &pixels[row * src_stride..row * src_stride + out_width];
+                let dst_start = (origin_y + row) * width + origin_x;
+                combined[dst_start..dst_start + out_width].copy_from_slice(src);
+            }
+        }
+        if needs_reacquire {
+            self.combined_outputs = None;
+        }
+        Ok((combined, (width, height)))
+    }
+
+    /// Capture a frame in its native high-dynamic-range format, via `IDXGIOutput5::DuplicateOutput1`,
+    /// instead of the 8-bit `BGRA8` path used by `capture_frame`.
+    ///
+    /// DXGI is asked for `DXGI_FORMAT_R16G16B16A16_FLOAT` in addition to the default 8-bit format,
+    /// so scRGB HDR and wide-gamut content can be captured losslessly; the caller is responsible
+    /// for any tone-mapping. Each channel in the returned pixels is the raw half-float bit
+    /// pattern reported by the duplication surface, not an 8-bit `BGRA8` reinterpretation.
+    ///
+    /// On success, return Vec with pixels and width and height of frame. On failure, return
+    /// CaptureError.
+    pub fn capture_frame_f16(&mut self) -> Result<(Vec<RGBA16F>, (usize, usize)), CaptureError> {
+        if let None = self.hdr_output {
+            if let Ok(_) = self.acquire_hdr_output_duplication() {
+                return Err(CaptureError::Fail("No valid duplicated output"));
+            } else {
+                return Err(CaptureError::RefreshFailure);
+            }
+        }
+        let timeout_ms = self.timeout_ms;
+        let capture_result = self
+            .hdr_output
+            .as_mut()
+            .unwrap()
+            .capture_frame_to_surface(timeout_ms);
+        match capture_result {
+            Ok((frame_surface, gpu_rotated)) => {
+                let hdr_output = self.hdr_output.as_ref().unwrap();
+                let output_desc = hdr_output.get_desc();
+                // DuplicateOutput1 was asked for DXGI_FORMAT_R16G16B16A16_FLOAT, but DXGI is free
+                // to hand back the 8-bit DXGI_FORMAT_B8G8R8A8_UNORM instead (and will, on any
+                // ordinary SDR display), in which case the surface holds BGRA8 pixels, not FP16
+                // ones, and must not be reinterpreted as such.
+                match hdr_output.negotiated_format() {
+                    Some(DXGI_FORMAT_R16G16B16A16_FLOAT) => {
+                        surface_to_pixel_buf_f16(frame_surface, gpu_rotated, &output_desc)
+                    }
+                    _ => Err(CaptureError::Fail(
+                        "Output did not negotiate DXGI_FORMAT_R16G16B16A16_FLOAT for HDR capture",
+                    )),
+                }
+            }
+            Err(DXGI_ERROR_ACCESS_LOST) => {
+                if let Ok(_) = self.acquire_hdr_output_duplication() {
+                    Err(CaptureError::AccessLost)
+                } else {
+                    Err(CaptureError::RefreshFailure)
+                }
+            }
+            Err(E_ACCESSDENIED) => Err(CaptureError::AccessDenied),
+            Err(DXGI_ERROR_WAIT_TIMEOUT) => Err(CaptureError::Timeout),
+            Err(_) => {
+                if let Ok(_) = self.acquire_hdr_output_duplication() {
+                    Err(CaptureError::Fail("Failure when acquiring frame"))
+                } else {
+                    Err(CaptureError::RefreshFailure)
+                }
+            }
+        }
+    }
 }
 
-use std::time::Instant;
 #[test]
 fn test() {
     let mut manager = DXGIManager::new(300).unwrap();
@@ -533,4 +2095,244 @@ fn compare_frame_dims() {
     assert_eq!(fw, fu8w);
     assert_eq!(fh, fu8h);
     assert_eq!(4 * frame.len(), frame_u8.len());
+}
+
+#[test]
+fn rotated_quad_uvs_is_identity_for_unknown_rotation() {
+    let identity = [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 1.0]];
+    assert_eq!(rotated_quad_uvs(DXGI_MODE_ROTATION_UNSPECIFIED), identity);
+    assert_eq!(rotated_quad_uvs(DXGI_MODE_ROTATION_IDENTITY), identity);
+}
+
+#[test]
+fn rotated_quad_uvs_covers_every_rotation_with_distinct_mappings() {
+    let rotations = [
+        DXGI_MODE_ROTATION_IDENTITY,
+        DXGI_MODE_ROTATION_ROTATE90,
+        DXGI_MODE_ROTATION_ROTATE180,
+        DXGI_MODE_ROTATION_ROTATE270,
+    ];
+    let uvs: Vec<_> = rotations.iter().map(|&r| rotated_quad_uvs(r)).collect();
+    for i in 0..uvs.len() {
+        for j in (i + 1)..uvs.len() {
+            assert_ne!(uvs[i], uvs[j], "rotations {} and {} map to the same UVs", i, j);
+        }
+    }
+}
+
+/// Rotates `src` (`src_w` by `src_h`, row-major) the same way `surface_to_pixel_buf`'s CPU
+/// fallback does for a non-identity rotation, used as a reference to check `rotated_quad_uvs`
+/// against below. `ROTATE90`/`ROTATE270` swap the returned dimensions, matching the width/height
+/// swap `surface_to_pixel_buf` applies before picking a rotation branch.
+fn cpu_rotate_reference(
+    src: &[i32],
+    src_w: usize,
+    src_h: usize,
+    rotation: DXGI_MODE_ROTATION,
+) -> (Vec<i32>, usize, usize) {
+    let (dst_w, dst_h) = match rotation {
+        DXGI_MODE_ROTATION_ROTATE90 | DXGI_MODE_ROTATION_ROTATE270 => (src_h, src_w),
+        _ => (src_w, src_h),
+    };
+    let mut dst = vec![0; dst_w * dst_h];
+    for y in 0..dst_h {
+        for x in 0..dst_w {
+            let (src_row, src_col) = match rotation {
+                DXGI_MODE_ROTATION_ROTATE90 => (src_h - 1 - x, y),
+                DXGI_MODE_ROTATION_ROTATE180 => (src_h - 1 - y, src_w - 1 - x),
+                DXGI_MODE_ROTATION_ROTATE270 => (x, src_w - 1 - y),
+                _ => (y, x),
+            };
+            dst[y * dst_w + x] = src[src_row * src_w + src_col];
+        }
+    }
+    (dst, dst_w, dst_h)
+}
+
+/// Samples `src` the way a full-screen quad textured with `rotated_quad_uvs(rotation)` would:
+/// bilinearly interpolate the quad's corner UVs across the destination pixel grid (using
+/// pixel-center coordinates, as a GPU sampler would), then snap to the nearest source texel.
+/// Used to check that `rotated_quad_uvs` asks the GPU to reproduce the same rotation the CPU
+/// fallback path implements by hand.
+fn gpu_quad_sample(
+    src: &[i32],
+    src_w: usize,
+    src_h: usize,
+    rotation: DXGI_MODE_ROTATION,
+) -> (Vec<i32>, usize, usize) {
+    let [tl, tr, bl, br] = rotated_quad_uvs(rotation);
+    let (dst_w, dst_h) = match rotation {
+        DXGI_MODE_ROTATION_ROTATE90 | DXGI_MODE_ROTATION_ROTATE270 => (src_h, src_w),
+        _ => (src_w, src_h),
+    };
+    let mut dst = vec![0; dst_w * dst_h];
+    for y in 0..dst_h {
+        for x in 0..dst_w {
+            let u = (x as f32 + 0.5) / dst_w as f32;
+            let v = (y as f32 + 0.5) / dst_h as f32;
+            let lerp = |corner: usize| {
+                tl[corner] * (1.0 - u) * (1.0 - v)
+                    + tr[corner] * u * (1.0 - v)
+                    + bl[corner] * (1.0 - u) * v
+                    + br[corner] * u * v
+            };
+            let src_col = ((lerp(0) * src_w as f32) as usize).min(src_w - 1);
+            let src_row = ((lerp(1) * src_h as f32) as usize).min(src_h - 1);
+            dst[y * dst_w + x] = src[src_row * src_w + src_col];
+        }
+    }
+    (dst, dst_w, dst_h)
+}
+
+#[test]
+fn rotated_quad_uvs_agrees_with_cpu_rotation_path() {
+    let (src_w, src_h) = (4, 3);
+    let src: Vec<i32> = (0..(src_w * src_h) as i32).collect();
+    for &rotation in &[
+        DXGI_MODE_ROTATION_ROTATE90,
+        DXGI_MODE_ROTATION_ROTATE180,
+        DXGI_MODE_ROTATION_ROTATE270,
+    ] {
+        let (cpu, cpu_w, cpu_h) = cpu_rotate_reference(&src, src_w, src_h, rotation);
+        let (gpu, gpu_w, gpu_h) = gpu_quad_sample(&src, src_w, src_h, rotation);
+        assert_eq!((gpu_w, gpu_h), (cpu_w, cpu_h), "rotation {:?}", rotation);
+        assert_eq!(gpu, cpu, "rotation {:?} disagrees with CPU rotation path", rotation);
+    }
+}
+
+fn no_cursor() -> CursorInfo {
+    CursorInfo {
+        visible: false,
+        position: (0, 0),
+        last_update_time: 0,
+        shape: None,
+    }
+}
+
+#[test]
+fn composite_cursor_is_noop_when_not_visible() {
+    let mut pixel_buf = vec![BGRA8 { b: 1, g: 2, r: 3, a: 4 }; 4];
+    composite_cursor(&mut pixel_buf, (2, 2), &no_cursor());
+    assert_eq!(pixel_buf, vec![BGRA8 { b: 1, g: 2, r: 3, a: 4 }; 4]);
+}
+
+#[test]
+fn composite_cursor_color_blends_by_alpha() {
+    let shape = CursorShape {
+        shape_type: PointerShapeType::Color,
+        width: 1,
+        height: 1,
+        pitch: 4,
+        hot_spot: (0, 0),
+        pixels: vec![200, 150, 100, 128], // b, g, r, a = 128 (half coverage)
+    };
+    let mut pixel_buf = vec![BGRA8 { b: 0, g: 0, r: 0, a: 255 }; 1];
+    let cursor = CursorInfo {
+        visible: true,
+        position: (0, 0),
+        last_update_time: 0,
+        shape: Some(shape),
+    };
+    composite_cursor(&mut pixel_buf, (1, 1), &cursor);
+    let blended = |s: u8| ((s as u32 * 128 + 0 * (255 - 128)) / 255) as u8;
+    assert_eq!(
+        pixel_buf[0],
+        BGRA8 {
+            b: blended(200),
+            g: blended(150),
+            r: blended(100),
+            a: 255,
+        }
+    );
+}
+
+#[test]
+fn composite_cursor_color_skips_pixels_outside_the_buffer() {
+    let shape = CursorShape {
+        shape_type: PointerShapeType::Color,
+        width: 1,
+        height: 1,
+        pitch: 4,
+        hot_spot: (0, 0),
+        pixels: vec![255, 255, 255, 255],
+    };
+    let mut pixel_buf = vec![BGRA8 { b: 9, g: 9, r: 9, a: 9 }; 1];
+    let cursor = CursorInfo {
+        visible: true,
+        position: (5, 5), // well outside the 1x1 buffer
+        last_update_time: 0,
+        shape: Some(shape),
+    };
+    composite_cursor(&mut pixel_buf, (1, 1), &cursor);
+    assert_eq!(pixel_buf[0], BGRA8 { b: 9, g: 9, r: 9, a: 9 });
+}
+
+#[test]
+fn composite_cursor_monochrome_applies_and_xor_masks() {
+    // 2x2 monochrome cursor: AND mask rows then XOR mask rows, each 1bpp-packed, MSB-first.
+    // Row 0: col0 (and=0,xor=0) -> black; col1 (and=1,xor=0) -> untouched.
+    // Row 1: col0 (and=1,xor=0) -> untouched; col1 (and=0,xor=0) -> black.
+    let shape = CursorShape {
+        shape_type: PointerShapeType::Monochrome,
+        width: 2,
+        height: 4, // 2 rows AND + 2 rows XOR
+        pitch: 1,
+        hot_spot: (0, 0),
+        pixels: vec![0b0100_0000, 0b1000_0000, 0b0000_0000, 0b0000_0000],
+    };
+    let mut pixel_buf = vec![BGRA8 { b: 10, g: 20, r: 30, a: 40 }; 4];
+    let cursor = CursorInfo {
+        visible: true,
+        position: (0, 0),
+        last_update_time: 0,
+        shape: Some(shape),
+    };
+    composite_cursor(&mut pixel_buf, (2, 2), &cursor);
+    assert_eq!(pixel_buf[0], BGRA8 { b: 0, g: 0, r: 0, a: 255 });
+    assert_eq!(pixel_buf[1], BGRA8 { b: 10, g: 20, r: 30, a: 40 });
+    assert_eq!(pixel_buf[2], BGRA8 { b: 10, g: 20, r: 30, a: 40 });
+    assert_eq!(pixel_buf[3], BGRA8 { b: 0, g: 0, r: 0, a: 255 });
+}
+
+#[test]
+fn rotate_rect_is_identity_for_unrotated_output() {
+    let rect = RECT { left: 10, top: 20, right: 30, bottom: 40 };
+    assert_eq!(rotate_rect(rect, DXGI_MODE_ROTATION_IDENTITY, 100, 200), rect);
+    assert_eq!(rotate_rect(rect, DXGI_MODE_ROTATION_UNSPECIFIED, 100, 200), rect);
+}
+
+#[test]
+fn rotate_rect_matches_rotated_corner_of_a_native_frame() {
+    // A 100x200 native (pre-rotation) frame, with a 1x1 rect marking its top-left native pixel.
+    // After each rotation, that native pixel should land at the corresponding corner of the
+    // rotated (upright) frame, whose dimensions are the native ones swapped for 90/270.
+    let top_left_pixel = RECT { left: 0, top: 0, right: 1, bottom: 1 };
+    let (native_w, native_h) = (100, 200);
+
+    // ROTATE90: native top-left ends up at the rotated frame's top-right corner.
+    let r = rotate_rect(top_left_pixel, DXGI_MODE_ROTATION_ROTATE90, native_w, native_h);
+    assert_eq!(r, RECT { left: native_h - 1, top: 0, right: native_h, bottom: 1 });
+
+    // ROTATE180: native top-left ends up at the rotated frame's bottom-right corner.
+    let r = rotate_rect(top_left_pixel, DXGI_MODE_ROTATION_ROTATE180, native_w, native_h);
+    assert_eq!(r, RECT { left: native_w - 1, top: native_h - 1, right: native_w, bottom: native_h });
+
+    // ROTATE270: native top-left ends up at the rotated frame's bottom-left corner.
+    let r = rotate_rect(top_left_pixel, DXGI_MODE_ROTATION_ROTATE270, native_w, native_h);
+    assert_eq!(r, RECT { left: 0, top: native_w - 1, right: 1, bottom: native_w });
+}
+
+#[test]
+fn rotate_move_rect_rotates_both_source_point_and_destination_rect() {
+    let (native_w, native_h) = (100, 200);
+    let move_rect = DXGI_OUTDUPL_MOVE_RECT {
+        SourcePoint: POINT { x: 0, y: 0 },
+        DestinationRect: RECT { left: 0, top: 0, right: 1, bottom: 1 },
+    };
+    let rotated = rotate_move_rect(move_rect, DXGI_MODE_ROTATION_ROTATE90, native_w, native_h);
+    assert_eq!(rotated.source_point, (native_h - 1, 0));
+    assert_eq!(
+        rotated.destination_rect,
+        DirtyRect { left: native_h - 1, top: 0, right: native_h, bottom: 1 }
+    );
 }
\ No newline at end of file